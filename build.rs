@@ -33,6 +33,8 @@ fn main() {
         // Allowlist the items we want to generate bindings for
         .allowlist_function("litert_lm_.*")
         .allowlist_type("LiteRtLm.*")
+        // Streaming callback typedef passed to the generate-stream entry point.
+        .allowlist_type("LiteRtLmTokenCallback")
         .allowlist_type("InputData.*")
         .allowlist_var("kInput.*")
         // Generate comments from C code
@@ -63,7 +65,16 @@ fn main() {
     }
 
     // 2. Link against libengine.so (the C library we built)
-    // This single library should contain or link to everything else
+    // This single library should contain or link to everything else.
+    //
+    // All C entry points declared in c/engine.h — including the streaming shim
+    // in c/engine.cc (litert_lm_session_generate_content_stream) — are compiled
+    // into this shared library as part of the upstream LiteRT-LM build, which
+    // links the C++ runtime the shim wraps. We do not compile engine.cc here:
+    // it depends on the runtime's C++ headers and must be built against the same
+    // tree as the rest of libengine. The c/ sources are bundled as the bindgen
+    // header source and as rerun triggers (below); the symbols come from the
+    // linked dylib.
     println!("cargo:rustc-link-lib=dylib=engine");
 
     // 3. Link C++ standard library (required for C++ code)
@@ -79,5 +90,7 @@ fn main() {
 
     println!("cargo:rerun-if-changed=../c/engine.h");
     println!("cargo:rerun-if-changed=../c/engine.cc");
+    println!("cargo:rerun-if-changed=c/engine.cc");
+    println!("cargo:rerun-if-changed=c/engine_internal.h");
     println!("cargo:rerun-if-env-changed=LITERT_LM_LIB_PATH");
 }
@@ -1,4 +1,4 @@
-use litert_lm::{Backend, Engine};
+use litert_lm::{Backend, Engine, SessionPool};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Get model path from command line argument
@@ -24,17 +24,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         "What is 2 + 2?",
     ];
 
-    println!("Running batch inference...\n");
+    // Run the prompts through a pool that cooperates with any parent
+    // `cargo`/`make -j N` jobserver and otherwise sizes itself to the machine.
+    let pool = SessionPool::new(&engine)?;
+    println!(
+        "Running batch inference (parallelism: {})...\n",
+        pool.parallelism()
+    );
     println!("========================================");
 
-    // Process each prompt in a separate session
-    for (i, prompt) in prompts.iter().enumerate() {
+    let results = pool.generate_batch(&prompts);
+    for (i, (prompt, result)) in prompts.iter().zip(results).enumerate() {
         println!("\n[{}] Prompt: {}", i + 1, prompt);
-
-        // Create a new session for each prompt
-        let session = engine.create_session()?;
-
-        match session.generate(prompt) {
+        match result {
             Ok(response) => {
                 println!("Response: {}", response);
             }
@@ -42,7 +44,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 eprintln!("Error: {}", e);
             }
         }
-
         println!("----------------------------------------");
     }
 
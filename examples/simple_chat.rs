@@ -1,5 +1,6 @@
 use litert_lm::{Backend, Engine};
 use std::io::{self, Write};
+use std::ops::ControlFlow;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Get model path from command line argument
@@ -47,13 +48,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         print!("Assistant: ");
         io::stdout().flush()?;
 
-        match session.generate(input) {
-            Ok(response) => {
-                println!("{}", response);
+        // Stream the response so chunks appear as they are decoded.
+        let result = session.generate_stream(input, |chunk| {
+            print!("{}", chunk);
+            let _ = io::stdout().flush();
+            ControlFlow::Continue(())
+        });
+
+        match result {
+            Ok(()) => {
+                println!();
                 println!();
             }
             Err(e) => {
-                eprintln!("Error generating response: {}", e);
+                eprintln!("\nError generating response: {}", e);
                 println!();
             }
         }
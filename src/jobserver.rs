@@ -0,0 +1,295 @@
+//! Cooperative concurrency limiting via the GNU make jobserver protocol.
+//!
+//! A backend already spins up its own thread pool for a single decode, so a
+//! process that runs many [`Session`](crate::Session)s in parallel can easily
+//! oversubscribe the machine. The jobserver protocol lets cooperating
+//! processes share a fixed pool of *job tokens* so that the total amount of
+//! work in flight stays bounded — exactly what `make -j N` and `cargo` use to
+//! keep a recursive build from fork-bombing the CPU.
+//!
+//! On startup we look at `CARGO_MAKEFLAGS`/`MAKEFLAGS` for an inherited
+//! jobserver (`--jobserver-auth=R,W` or the older `--jobserver-fds=R,W`, naming
+//! a pair of pipe file descriptors). If one is found we connect as a client and
+//! share the parent's token budget; otherwise we create a private jobserver
+//! seeded with [`available_parallelism`](std::thread::available_parallelism)
+//! tokens.
+//!
+//! The pool is a pipe pre-filled with one byte per token. Acquiring a slot is a
+//! blocking read of a single byte; releasing writes the byte back. Every
+//! process additionally owns one *implicit* token — its own slot — which is
+//! tracked separately and must never be written into the pipe, or we would hand
+//! the parent more tokens than it handed out.
+
+use std::sync::Arc;
+
+/// A handle to the shared job-token pool.
+///
+/// Obtain one with [`JobServer::from_env_or_local`]. Clone the [`Arc`] to share
+/// it between worker threads; every clone draws from the same token budget.
+pub(crate) struct JobServer {
+    inner: imp::Inner,
+    /// The implicit token owned by this process. It is claimed without touching
+    /// the pipe and, crucially, is never written back into it on release.
+    implicit: std::sync::atomic::AtomicBool,
+    parallelism: usize,
+}
+
+/// RAII guard representing one held job slot.
+///
+/// The slot is returned to the pool when the guard is dropped — including on
+/// error or panic — so callers cannot leak a token out of the pipe.
+pub(crate) struct Acquired {
+    server: Arc<JobServer>,
+    /// Whether this guard holds the implicit token (released in-process) or a
+    /// token read out of the pipe (written back on drop).
+    implicit: bool,
+}
+
+impl JobServer {
+    /// Connect to an inherited jobserver if one is advertised in the
+    /// environment, otherwise create a private one sized to the machine.
+    pub(crate) fn from_env_or_local() -> std::io::Result<Arc<JobServer>> {
+        match imp::from_env()? {
+            Some(server) => Ok(Arc::new(server)),
+            None => Ok(Arc::new(imp::local()?)),
+        }
+    }
+
+    /// The number of job slots this process may occupy concurrently, counting
+    /// the implicit token. For an inherited jobserver this is a best-effort
+    /// estimate, since the parent does not tell clients the pool size.
+    pub(crate) fn parallelism(&self) -> usize {
+        self.parallelism
+    }
+
+    /// Acquire a single job slot, blocking until one is available.
+    ///
+    /// The implicit token is handed out first (no I/O); further callers block
+    /// on a one-byte read from the pipe.
+    pub(crate) fn acquire(self: &Arc<Self>) -> std::io::Result<Acquired> {
+        use std::sync::atomic::Ordering;
+
+        // Claim the implicit token without touching the pipe, if it is free.
+        if self
+            .implicit
+            .compare_exchange(true, false, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            return Ok(Acquired {
+                server: Arc::clone(self),
+                implicit: true,
+            });
+        }
+
+        imp::acquire_token(&self.inner)?;
+        Ok(Acquired {
+            server: Arc::clone(self),
+            implicit: false,
+        })
+    }
+}
+
+impl Drop for Acquired {
+    fn drop(&mut self) {
+        use std::sync::atomic::Ordering;
+
+        if self.implicit {
+            // Return the implicit token to ourselves; never to the pipe.
+            self.server.implicit.store(true, Ordering::Release);
+        } else {
+            // Best effort: a failed write during teardown would only shrink the
+            // pool, and there is nothing useful to do with the error here.
+            let _ = imp::release_token(&self.server.inner);
+        }
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::env;
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use std::os::fd::{BorrowedFd, FromRawFd, OwnedFd};
+
+    use super::JobServer;
+
+    /// The read and write ends of the token pipe.
+    pub(super) struct Inner {
+        read: File,
+        write: File,
+    }
+
+    pub(super) fn from_env() -> std::io::Result<Option<JobServer>> {
+        let Some((read_fd, write_fd)) = parse_env() else {
+            return Ok(None);
+        };
+
+        // A jobserver may advertise `--jobserver-auth` without actually passing
+        // the pipe down to this process (e.g. a non-recursive `make` recipe).
+        // Reading or writing an unrelated/closed descriptor would block forever
+        // or corrupt it, so fall back to a private pool unless both ends are
+        // genuinely open pipes.
+        if !is_pipe(read_fd) || !is_pipe(write_fd) {
+            return Ok(None);
+        }
+
+        // Duplicate the inherited descriptors so our `File` handles own their
+        // own copies and closing them cannot disturb the parent's pipe.
+        let read = dup(read_fd)?;
+        let write = dup(write_fd)?;
+
+        Ok(Some(JobServer {
+            inner: Inner { read, write },
+            implicit: true.into(),
+            // The parent owns the real budget; fall back to the machine size as
+            // an advisory figure for callers that query it.
+            parallelism: super::available(),
+        }))
+    }
+
+    pub(super) fn local() -> std::io::Result<JobServer> {
+        let parallelism = super::available();
+        let (reader, writer) = std::io::pipe()?;
+        let read = File::from(OwnedFd::from(reader));
+        let mut write = File::from(OwnedFd::from(writer));
+
+        // Seed the pipe with one token per slot beyond the implicit one, so the
+        // implicit token plus the pipe together add up to `parallelism`.
+        for _ in 0..parallelism.saturating_sub(1) {
+            write.write_all(&[b'|'])?;
+        }
+
+        Ok(JobServer {
+            inner: Inner { read, write },
+            implicit: true.into(),
+            parallelism,
+        })
+    }
+
+    pub(super) fn acquire_token(inner: &Inner) -> std::io::Result<()> {
+        let mut byte = [0u8; 1];
+        let mut read = &inner.read;
+        read.read_exact(&mut byte)
+    }
+
+    pub(super) fn release_token(inner: &Inner) -> std::io::Result<()> {
+        let mut write = &inner.write;
+        write.write_all(&[b'|'])
+    }
+
+    /// Parse the jobserver descriptor pair out of the make environment.
+    fn parse_env() -> Option<(i32, i32)> {
+        let flags = env::var("CARGO_MAKEFLAGS")
+            .or_else(|_| env::var("MAKEFLAGS"))
+            .ok()?;
+
+        // MAKEFLAGS packs clustered short flags (`rR`, `-j`) ahead of the
+        // jobserver descriptor, so we must scan past non-matching tokens rather
+        // than bailing on the first one. Likewise a malformed or named-pipe
+        // (`fifo:`) entry should not abort detection of a later valid pair.
+        for arg in flags.split_ascii_whitespace() {
+            let Some(fds) = arg
+                .strip_prefix("--jobserver-auth=")
+                .or_else(|| arg.strip_prefix("--jobserver-fds="))
+            else {
+                continue;
+            };
+            let Some((read, write)) = fds.split_once(',') else {
+                continue;
+            };
+            let (Ok(read), Ok(write)) = (read.trim().parse(), write.trim().parse()) else {
+                continue;
+            };
+            return Some((read, write));
+        }
+        None
+    }
+
+    /// Check that an inherited descriptor is actually an open pipe end.
+    ///
+    /// `lseek` distinguishes the cases cheaply and portably: a pipe reports
+    /// `ESPIPE`, a seekable regular file succeeds (so it is *not* a jobserver
+    /// pipe), and a closed descriptor reports `EBADF` — both of the latter mean
+    /// we should not trust it.
+    fn is_pipe(fd: i32) -> bool {
+        const SEEK_CUR: i32 = 1;
+        // ESPIPE is 29 on both Linux and macOS.
+        const ESPIPE: i32 = 29;
+
+        extern "C" {
+            fn lseek(fd: i32, offset: i64, whence: i32) -> i64;
+        }
+
+        // Safety: `lseek` only queries the descriptor's offset; it has no memory
+        // effects and leaves a pipe untouched (it fails with ESPIPE).
+        let pos = unsafe { lseek(fd, 0, SEEK_CUR) };
+        pos == -1 && std::io::Error::last_os_error().raw_os_error() == Some(ESPIPE)
+    }
+
+    fn dup(fd: i32) -> std::io::Result<File> {
+        // Safety: the fd names a pipe end inherited from the parent jobserver;
+        // `try_clone_to_owned` dups it without taking ownership of the original.
+        let owned = unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned()?;
+        // Safety: `owned` is a fresh, exclusively-owned descriptor.
+        Ok(unsafe { File::from_raw_fd(owned_into_raw(owned)) })
+    }
+
+    fn owned_into_raw(fd: OwnedFd) -> i32 {
+        use std::os::fd::IntoRawFd;
+        fd.into_raw_fd()
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use std::sync::{Condvar, Mutex};
+
+    use super::JobServer;
+
+    /// A portable in-process fallback used on platforms where we do not speak
+    /// the descriptor-based jobserver protocol. It bounds concurrency within
+    /// this process but does not interoperate with a parent jobserver.
+    pub(super) struct Inner {
+        tokens: Mutex<usize>,
+        available: Condvar,
+    }
+
+    pub(super) fn from_env() -> std::io::Result<Option<JobServer>> {
+        // Named-pipe jobservers are not supported by this fallback.
+        Ok(None)
+    }
+
+    pub(super) fn local() -> std::io::Result<JobServer> {
+        let parallelism = super::available();
+        Ok(JobServer {
+            inner: Inner {
+                tokens: Mutex::new(parallelism.saturating_sub(1)),
+                available: Condvar::new(),
+            },
+            implicit: true.into(),
+            parallelism,
+        })
+    }
+
+    pub(super) fn acquire_token(inner: &Inner) -> std::io::Result<()> {
+        let mut tokens = inner.tokens.lock().unwrap();
+        while *tokens == 0 {
+            tokens = inner.available.wait(tokens).unwrap();
+        }
+        *tokens -= 1;
+        Ok(())
+    }
+
+    pub(super) fn release_token(inner: &Inner) -> std::io::Result<()> {
+        *inner.tokens.lock().unwrap() += 1;
+        inner.available.notify_one();
+        Ok(())
+    }
+}
+
+/// Machine parallelism, clamped to at least one slot.
+fn available() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
@@ -32,6 +32,14 @@
 use std::ffi::{CStr, CString};
 use std::fmt;
 
+mod jobserver;
+mod profiler;
+
+use jobserver::JobServer;
+use std::sync::Arc;
+
+pub use profiler::{ProfileReport, Profiler, Span};
+
 // Include auto-generated bindings from bindgen
 #[allow(non_upper_case_globals)]
 #[allow(non_camel_case_types)]
@@ -266,6 +274,97 @@ impl Session {
         }
     }
 
+    /// Generate text, delivering each decoded chunk through a callback as it
+    /// is produced rather than waiting for the full response.
+    ///
+    /// `on_token` is invoked once per decoded chunk with the newly produced
+    /// text. Return [`ControlFlow::Continue`] to keep decoding or
+    /// [`ControlFlow::Break`] to abort generation early — handy for interactive
+    /// UIs that want to show progress and stop on user input.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The input text prompt
+    /// * `on_token` - Called with each chunk; controls early cancellation
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use litert_lm::{Engine, Backend};
+    /// use std::ops::ControlFlow;
+    ///
+    /// let engine = Engine::new("model.tflite", Backend::Cpu)?;
+    /// let session = engine.create_session()?;
+    /// session.generate_stream("Tell me a story.", |chunk| {
+    ///     print!("{}", chunk);
+    ///     ControlFlow::Continue(())
+    /// })?;
+    /// # Ok::<(), litert_lm::Error>(())
+    /// ```
+    pub fn generate_stream<F>(&self, prompt: &str, mut on_token: F) -> Result<()>
+    where
+        F: FnMut(&str) -> std::ops::ControlFlow<()>,
+    {
+        let prompt_cstr = CString::new(prompt)
+            .map_err(|e| Error::new(format!("Invalid prompt: {}", e)))?;
+
+        // Box the trampoline state so it has a stable address for the duration
+        // of the (blocking) C call; `on_token` is borrowed, not moved into it.
+        let mut state = Box::new(StreamState {
+            on_token: &mut on_token,
+            panic: None,
+        });
+
+        let status = unsafe {
+            let input_data = InputData {
+                type_: InputDataType_kInputText,
+                data: prompt_cstr.as_ptr() as *const std::ffi::c_void,
+                size: prompt.len(),
+            };
+
+            litert_lm_session_generate_content_stream(
+                self.raw,
+                &input_data,
+                1,
+                Some(token_trampoline),
+                &mut *state as *mut StreamState as *mut std::ffi::c_void,
+            )
+        };
+
+        // Re-raise a panic that unwound into the callback, now that we are back
+        // on the Rust side of the FFI boundary.
+        if let Some(payload) = state.panic.take() {
+            std::panic::resume_unwind(payload);
+        }
+
+        if status != 0 {
+            return Err(Error::new("Failed to stream generated content"));
+        }
+
+        Ok(())
+    }
+
+    /// Create a profiler for instrumenting generations on this session.
+    ///
+    /// The profiler records per-phase spans (prefill, decode steps,
+    /// detokenization) and produces a [`ProfileReport`] that can be dumped as
+    /// Chrome-tracing JSON or a human-readable table.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use litert_lm::{Engine, Backend};
+    ///
+    /// let engine = Engine::new("model.tflite", Backend::Cpu)?;
+    /// let session = engine.create_session()?;
+    /// let report = session.profile().generate("Hello!")?;
+    /// print!("{}", report.to_time_passes_table());
+    /// # Ok::<(), litert_lm::Error>(())
+    /// ```
+    pub fn profile(&self) -> Profiler<'_> {
+        Profiler::new(self)
+    }
+
     /// Get benchmark information (if benchmarking is enabled)
     ///
     /// Returns information about performance metrics like tokens per second.
@@ -303,6 +402,148 @@ impl Drop for Session {
     }
 }
 
+/// State threaded through the C streaming callback as its `void* user_data`.
+///
+/// Holds the caller's closure and a slot to stash a panic payload, so an unwind
+/// that originates inside the callback can be carried back across the FFI
+/// boundary and resumed on the Rust side instead of crossing `extern "C"`.
+struct StreamState<'a> {
+    on_token: &'a mut dyn FnMut(&str) -> std::ops::ControlFlow<()>,
+    panic: Option<Box<dyn std::any::Any + Send>>,
+}
+
+/// `extern "C"` shim invoked by the C decoder for each produced chunk.
+///
+/// Returns `true` to continue decoding and `false` to stop, mapping the
+/// caller's [`ControlFlow`](std::ops::ControlFlow). Any panic is caught and
+/// stored in the [`StreamState`] rather than allowed to unwind into C.
+unsafe extern "C" fn token_trampoline(
+    token: *const std::os::raw::c_char,
+    user_data: *mut std::ffi::c_void,
+) -> bool {
+    use std::ops::ControlFlow;
+    use std::panic::AssertUnwindSafe;
+
+    // Safety: `user_data` is the `&mut StreamState` handed to the C call, which
+    // outlives every callback invocation.
+    let state = unsafe { &mut *(user_data as *mut StreamState) };
+
+    // A previous callback already panicked; tear down as fast as possible.
+    if state.panic.is_some() {
+        return false;
+    }
+
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        let chunk = if token.is_null() {
+            std::borrow::Cow::Borrowed("")
+        } else {
+            // Safety: non-null `token` points at a NUL-terminated C string that
+            // stays valid for the duration of this call.
+            unsafe { CStr::from_ptr(token) }.to_string_lossy()
+        };
+        (state.on_token)(chunk.as_ref())
+    }));
+
+    match result {
+        Ok(ControlFlow::Continue(())) => true,
+        Ok(ControlFlow::Break(())) => false,
+        Err(payload) => {
+            state.panic = Some(payload);
+            false
+        }
+    }
+}
+
+// ============================================================================
+// Session Pool
+// ============================================================================
+
+/// A bounded pool that runs batch inference across several sessions at once
+/// while cooperating with a parent `cargo`/`make -j N` invocation.
+///
+/// Each decode already drives a backend thread pool, so running many sessions
+/// in parallel can oversubscribe the machine. The pool integrates the GNU make
+/// jobserver protocol: if the process was launched under a jobserver (via
+/// `CARGO_MAKEFLAGS`/`MAKEFLAGS`) it shares that token budget, otherwise it
+/// creates a private one sized to [`available_parallelism`]. A worker must hold
+/// a job token for the entire [`litert_lm_session_generate_content`] call, so
+/// no more sessions decode concurrently than there are tokens in the pool. For
+/// a private pool that is [`parallelism`](SessionPool::parallelism); under an
+/// inherited jobserver it is the parent's token budget.
+///
+/// [`available_parallelism`]: std::thread::available_parallelism
+///
+/// # Example
+///
+/// ```no_run
+/// use litert_lm::{Engine, Backend, SessionPool};
+///
+/// let engine = Engine::new("model.tflite", Backend::Cpu)?;
+/// let pool = SessionPool::new(&engine)?;
+/// let results = pool.generate_batch(&["What is 2+2?", "Say hello."]);
+/// # Ok::<(), litert_lm::Error>(())
+/// ```
+pub struct SessionPool<'e> {
+    engine: &'e Engine,
+    jobserver: Arc<JobServer>,
+}
+
+impl<'e> SessionPool<'e> {
+    /// Create a pool over an existing engine.
+    ///
+    /// Connects to an inherited jobserver if one is advertised in the
+    /// environment, otherwise seeds a private pool with as many tokens as the
+    /// machine has logical cores.
+    pub fn new(engine: &'e Engine) -> Result<Self> {
+        let jobserver = JobServer::from_env_or_local()
+            .map_err(|e| Error::new(format!("Failed to start jobserver: {}", e)))?;
+
+        Ok(SessionPool { engine, jobserver })
+    }
+
+    /// The maximum number of sessions that may decode at once with a private
+    /// pool. Under an inherited jobserver this is the machine's parallelism,
+    /// reported as an estimate, since the parent does not publish its budget.
+    pub fn parallelism(&self) -> usize {
+        self.jobserver.parallelism()
+    }
+
+    /// Generate a response for each prompt, bounding how many run concurrently.
+    ///
+    /// One worker thread is spawned per prompt; each acquires a job token,
+    /// creates its own session, runs [`Session::generate`], and releases the
+    /// token. Results are returned in the same order as `prompts`, with each
+    /// prompt's error isolated to its own slot.
+    pub fn generate_batch(&self, prompts: &[&str]) -> Vec<Result<String>> {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = prompts
+                .iter()
+                .map(|prompt| {
+                    let engine = self.engine;
+                    let jobserver = &self.jobserver;
+                    scope.spawn(move || {
+                        // Hold the token for the whole generate call; the guard
+                        // returns it to the pool on every exit path.
+                        let _token = jobserver
+                            .acquire()
+                            .map_err(|e| Error::new(format!("Failed to acquire job slot: {}", e)))?;
+                        let session = engine.create_session()?;
+                        session.generate(prompt)
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| match handle.join() {
+                    Ok(result) => result,
+                    Err(_) => Err(Error::new("Worker thread panicked during generation")),
+                })
+                .collect()
+        })
+    }
+}
+
 // ============================================================================
 // Benchmark Info
 // ============================================================================
@@ -337,4 +578,47 @@ mod tests {
         let err = Error::new("test error");
         assert_eq!(format!("{}", err), "LiteRT-LM Error: test error");
     }
+
+    #[test]
+    fn test_jobserver_recycles_tokens() {
+        // A local jobserver reports at least one slot and hands a freed token
+        // back to the next acquirer.
+        let server = JobServer::from_env_or_local().expect("jobserver");
+        assert!(server.parallelism() >= 1);
+
+        let first = server.acquire().expect("acquire implicit token");
+        drop(first);
+        // The implicit token is now back in the pool and reusable.
+        let _second = server.acquire().expect("reacquire released token");
+    }
+
+    #[test]
+    fn test_profile_report_chrome_trace() {
+        use std::time::Duration;
+
+        let report = ProfileReport {
+            output: "hi".to_string(),
+            spans: vec![Span {
+                name: "prefill".to_string(),
+                start: Duration::ZERO,
+                duration: Duration::from_millis(5),
+                tid: 0,
+            }],
+            total: Duration::from_millis(5),
+            prefill: Duration::from_millis(5),
+            decode: Duration::ZERO,
+            prefill_turns_per_sec: None,
+            decode_tokens_per_sec: None,
+            peak_rss_bytes: Some(2 * 1024 * 1024),
+        };
+
+        let json = report.to_chrome_trace_json();
+        assert!(json.contains("\"name\":\"prefill\""));
+        assert!(json.contains("\"ph\":\"X\""));
+        assert!(json.contains("\"dur\":5000"));
+
+        let table = report.to_time_passes_table();
+        assert!(table.contains("prefill"));
+        assert!(table.contains("peak-rss: 2 MB"));
+    }
 }
@@ -0,0 +1,306 @@
+//! Structured self-profiling for a single generate call.
+//!
+//! [`BenchmarkInfo`](crate::BenchmarkInfo) only exposes time-to-first-token and
+//! turn counts, which is too coarse for working out where latency actually
+//! goes. This module adds an event-based profiler in the spirit of rustc's
+//! self-profiler and its "time passes" report: a [`Profiler`] records
+//! timestamped [`Span`]s for each phase of a generation — prefill (which
+//! subsumes tokenization), every decode step, and detokenization — and rolls
+//! them up into a [`ProfileReport`] with per-phase wall-clock, separate
+//! prefill/decode throughput, and the peak resident-set size sampled
+//! repeatedly across the call.
+//!
+//! The report can be dumped as Chrome-tracing JSON (openable in a trace viewer)
+//! or as a plain table modelled on rustc's `print_time_passes_entry` output.
+
+use std::time::{Duration, Instant};
+
+use crate::{Result, Session};
+
+/// A single timed phase within a generate call.
+#[derive(Debug, Clone)]
+pub struct Span {
+    /// Human-readable phase name (e.g. `"prefill"`, `"decode"`).
+    pub name: String,
+    /// Offset of this span's start from the beginning of the run.
+    pub start: Duration,
+    /// How long the phase took.
+    pub duration: Duration,
+    /// Thread id this span is attributed to, for the trace viewer's lanes.
+    pub tid: u64,
+}
+
+/// A handle for profiling generations on a [`Session`].
+///
+/// Obtain one with [`Session::profile`]. Each call to [`generate`] produces an
+/// independent [`ProfileReport`].
+///
+/// [`generate`]: Profiler::generate
+pub struct Profiler<'s> {
+    session: &'s Session,
+}
+
+impl<'s> Profiler<'s> {
+    pub(crate) fn new(session: &'s Session) -> Self {
+        Profiler { session }
+    }
+
+    /// Run a generation, recording a span for each phase, and return the
+    /// response alongside a [`ProfileReport`].
+    ///
+    /// Decode steps are timed by streaming the response
+    /// ([`Session::generate_stream`]); the prefill span runs from the start of
+    /// the call to the first chunk and includes tokenization, which the C API
+    /// does not surface as a separate phase. Detokenization covers the tail
+    /// after the last chunk. Prefill throughput is derived from the session's
+    /// [`BenchmarkInfo`](crate::BenchmarkInfo) prefill turn count when
+    /// available; decode throughput counts the streamed chunks after the first.
+    ///
+    /// Resident-set size is sampled at the start, around each produced chunk,
+    /// and at completion so the reported peak reflects a transient high-water
+    /// mark during decode rather than just the endpoints.
+    pub fn generate(&self, prompt: &str) -> Result<ProfileReport> {
+        use std::ops::ControlFlow;
+
+        let start = Instant::now();
+
+        let mut spans: Vec<Span> = Vec::new();
+        let mut output = String::new();
+        let mut first_token_at: Option<Instant> = None;
+        let mut last = start;
+        let mut decode_tokens: usize = 0;
+        let mut peak_rss_bytes = sample_rss();
+
+        self.session.generate_stream(prompt, |chunk| {
+            let now = Instant::now();
+            output.push_str(chunk);
+            if first_token_at.is_none() {
+                first_token_at = Some(now);
+                // Prefill subsumes tokenization: both cover everything up to the
+                // first produced chunk, which the C API does not break apart.
+                spans.push(Span {
+                    name: "prefill".to_string(),
+                    start: Duration::ZERO,
+                    duration: now.duration_since(start),
+                    tid: 0,
+                });
+            } else {
+                // Each streamed chunk past the first is one decoded token.
+                decode_tokens += 1;
+                spans.push(Span {
+                    name: "decode".to_string(),
+                    start: last.duration_since(start),
+                    duration: now.duration_since(last),
+                    tid: 1,
+                });
+            }
+            last = now;
+            // Sample RSS on every chunk so a mid-run peak is not missed.
+            peak_rss_bytes = peak_rss_bytes.max(sample_rss());
+            ControlFlow::Continue(())
+        })?;
+
+        let end = Instant::now();
+        // Detokenization: the tail between the last chunk and completion.
+        spans.push(Span {
+            name: "detokenization".to_string(),
+            start: last.duration_since(start),
+            duration: end.duration_since(last),
+            tid: 1,
+        });
+
+        peak_rss_bytes = peak_rss_bytes.max(sample_rss());
+
+        let total = end.duration_since(start);
+        let prefill = first_token_at
+            .map(|t| t.duration_since(start))
+            .unwrap_or(total);
+        let decode = total.saturating_sub(prefill);
+
+        // Prefill throughput is derived from the prefill turn count the C API
+        // tracks (turns, not tokens). Decode throughput is a genuine tokens/sec
+        // from the number of streamed chunks, each of which is one decoded
+        // token, over the real decode duration.
+        let bench = self.session.get_benchmark_info().ok();
+        let prefill_turns_per_sec = bench
+            .as_ref()
+            .map(|b| throughput(b.num_prefill_turns, prefill));
+        let decode_tokens_per_sec = Some(throughput(decode_tokens, decode));
+
+        Ok(ProfileReport {
+            output,
+            spans,
+            total,
+            prefill,
+            decode,
+            prefill_turns_per_sec,
+            decode_tokens_per_sec,
+            peak_rss_bytes,
+        })
+    }
+}
+
+/// The result of profiling a single generate call.
+#[derive(Debug, Clone)]
+pub struct ProfileReport {
+    /// The generated text.
+    pub output: String,
+    /// One span per recorded phase.
+    pub spans: Vec<Span>,
+    /// Total wall-clock time for the generation.
+    pub total: Duration,
+    /// Wall-clock time spent in prefill (up to the first token).
+    pub prefill: Duration,
+    /// Wall-clock time spent decoding (first token to completion).
+    pub decode: Duration,
+    /// Prefill throughput in turns/sec, if turn counts were available.
+    pub prefill_turns_per_sec: Option<f64>,
+    /// Decode throughput in tokens/sec, from the number of streamed chunks.
+    pub decode_tokens_per_sec: Option<f64>,
+    /// Peak resident-set size in bytes, sampled repeatedly across the call.
+    pub peak_rss_bytes: Option<u64>,
+}
+
+impl ProfileReport {
+    /// Serialize the spans as a Chrome-tracing JSON array.
+    ///
+    /// Each span becomes a complete (`"ph": "X"`) event with microsecond
+    /// `ts`/`dur`, so the output can be loaded directly into a trace viewer
+    /// such as `chrome://tracing` or Perfetto.
+    pub fn to_chrome_trace_json(&self) -> String {
+        let pid = std::process::id();
+        let mut out = String::from("[\n");
+        for (i, span) in self.spans.iter().enumerate() {
+            if i > 0 {
+                out.push_str(",\n");
+            }
+            out.push_str(&format!(
+                "  {{\"name\":\"{}\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":{},\"tid\":{}}}",
+                escape(&span.name),
+                span.start.as_micros(),
+                span.duration.as_micros(),
+                pid,
+                span.tid,
+            ));
+        }
+        out.push_str("\n]\n");
+        out
+    }
+
+    /// Render a human-readable summary modelled on rustc's time-passes output.
+    pub fn to_time_passes_table(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&time_pass_line("prefill", self.prefill, self.prefill_turns_per_sec, "turns/s"));
+        out.push_str(&time_pass_line("decode", self.decode, self.decode_tokens_per_sec, "tok/s"));
+        out.push_str(&time_pass_line("total", self.total, None, ""));
+        if let Some(rss) = self.peak_rss_bytes {
+            out.push_str(&format!("peak-rss: {} MB\n", rss / (1024 * 1024)));
+        }
+        out
+    }
+}
+
+/// Format one `time: …` line in the rustc `print_time_passes_entry` style.
+///
+/// `unit` labels the optional rate (e.g. `"tok/s"`, `"turns/s"`).
+fn time_pass_line(name: &str, dur: Duration, rate: Option<f64>, unit: &str) -> String {
+    match rate {
+        Some(r) => format!(
+            "time: {:>8.3}s  {:>8.1} {:<7} {}\n",
+            dur.as_secs_f64(),
+            r,
+            unit,
+            name
+        ),
+        None => format!("time: {:>8.3}s  {:>17} {}\n", dur.as_secs_f64(), "", name),
+    }
+}
+
+/// Rate per second (tokens or turns), guarding against a zero-length phase.
+fn throughput(count: usize, dur: Duration) -> f64 {
+    let secs = dur.as_secs_f64();
+    if secs > 0.0 {
+        count as f64 / secs
+    } else {
+        0.0
+    }
+}
+
+/// Escape a span name for embedding in a JSON string literal.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Sample the current resident-set size in bytes, or `None` if unavailable.
+#[cfg(target_os = "linux")]
+fn sample_rss() -> Option<u64> {
+    // The second field of /proc/self/statm is the resident set in pages.
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let resident_pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // Linux uses a 4 KiB page on every architecture we target.
+    Some(resident_pages * 4096)
+}
+
+/// Sample the current resident-set size in bytes, or `None` if unavailable.
+#[cfg(target_os = "macos")]
+fn sample_rss() -> Option<u64> {
+    // mach_task_basic_info, as reported by task_info(MACH_TASK_BASIC_INFO).
+    #[repr(C)]
+    struct MachTaskBasicInfo {
+        virtual_size: u64,
+        resident_size: u64,
+        resident_size_max: u64,
+        user_time: [i32; 2],
+        system_time: [i32; 2],
+        policy: i32,
+        suspend_count: i32,
+    }
+
+    const MACH_TASK_BASIC_INFO: u32 = 20;
+    // info struct size measured in natural_t (u32) units.
+    let count = (std::mem::size_of::<MachTaskBasicInfo>() / std::mem::size_of::<u32>()) as u32;
+
+    extern "C" {
+        static mach_task_self_: u32;
+        fn task_info(
+            target_task: u32,
+            flavor: u32,
+            task_info_out: *mut u32,
+            task_info_count: *mut u32,
+        ) -> i32;
+    }
+
+    let mut info = MachTaskBasicInfo {
+        virtual_size: 0,
+        resident_size: 0,
+        resident_size_max: 0,
+        user_time: [0; 2],
+        system_time: [0; 2],
+        policy: 0,
+        suspend_count: 0,
+    };
+    let mut out_count = count;
+
+    // Safety: `info` is large enough for `out_count` natural_t words, which we
+    // pass by value/pointer exactly as task_info expects.
+    let status = unsafe {
+        task_info(
+            mach_task_self_,
+            MACH_TASK_BASIC_INFO,
+            &mut info as *mut MachTaskBasicInfo as *mut u32,
+            &mut out_count,
+        )
+    };
+
+    if status == 0 {
+        Some(info.resident_size)
+    } else {
+        None
+    }
+}
+
+/// Sample the current resident-set size in bytes, or `None` if unavailable.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn sample_rss() -> Option<u64> {
+    None
+}